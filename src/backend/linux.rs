@@ -0,0 +1,72 @@
+//! Linux backend: `/dev/cpu/n/msr`, accessed with `pread`/`pwrite` at the
+//! MSR index as the file offset. This is the original implementation the
+//! crate started with, now behind `MsrBackend`.
+
+use super::MsrBackend;
+
+pub struct LinuxMsr {
+    fd: i32,
+}
+
+impl MsrBackend for LinuxMsr {
+    fn open(core_id: usize) -> Result<Self, &'static str> {
+        let path = format!("/dev/cpu/{}/msr", core_id);
+        // O_RDWR, not O_RDONLY: probe_access_class's write-back pwrite()
+        // needs a writable fd, or it fails with EBADF at the VFS layer
+        // before ever reaching the MSR driver (so every MSR would be
+        // classified as `ReadOnly`, since `write` reports that EBADF as
+        // an unsupported MSR).
+        match nix::fcntl::open(path.as_str(), nix::fcntl::OFlag::O_RDWR,
+                               nix::sys::stat::Mode::S_IRUSR) {
+            Ok(fd) => Ok(LinuxMsr { fd }),
+            Err(e) => match e {
+                nix::Error::Sys(eno) => match eno {
+                    nix::errno::Errno::EACCES => Err("Permission denied"),
+                    _ => panic!("{}", eno),
+                },
+                _ => panic!("{}", e),
+            },
+        }
+    }
+
+    fn read(&self, msr: u32) -> Result<u64, &'static str> {
+        let mut buf = [0u8; 8];
+        match nix::sys::uio::pread(self.fd, &mut buf, msr as i64) {
+            Ok(_) => Ok(u64::from_le_bytes(buf)),
+            Err(e) => match e {
+                nix::Error::Sys(eno) => match eno {
+                    nix::errno::Errno::EIO => Err("Unsupported MSR"),
+                    _ => panic!("{}", eno),
+                },
+                _ => panic!("{}", e),
+            },
+        }
+    }
+
+    fn write(&self, msr: u32, val: u64) -> Result<(), &'static str> {
+        let buf = val.to_le_bytes();
+        match nix::sys::uio::pwrite(self.fd, &buf, msr as i64) {
+            Ok(_) => Ok(()),
+            Err(e) => match e {
+                nix::Error::Sys(eno) => match eno {
+                    // EIO is a faulting WRMSR reaching the driver; EBADF is
+                    // the VFS rejecting the write before it ever gets there
+                    // (e.g. a non-writable fd). Probing must catch both
+                    // rather than crash, so a write probe is never the
+                    // thing that brings this tool down.
+                    nix::errno::Errno::EIO | nix::errno::Errno::EBADF => Err("Unsupported MSR"),
+                    _ => panic!("{}", eno),
+                },
+                _ => panic!("{}", e),
+            },
+        }
+    }
+
+    fn close(self) {
+        use nix::unistd::close;
+        match close(self.fd) {
+            Ok(_) => {},
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Platform-specific MSR access, behind a single [`MsrBackend`] trait.
+//!
+//! Every OS exposes RDMSR/WRMSR to userspace differently (a `pread`/`pwrite`
+//! device on Linux, an ioctl-driven device on the BSDs, nothing at all on
+//! OpenBSD), but callers shouldn't have to care. This mirrors the
+//! multiplexing flashrom does over its `rdmsr(addr) -> {lo, hi}` surface for
+//! Linux/OpenBSD/FreeBSD/OSX.
+//!
+//! Every implementation classifies a faulting access as
+//! `Err("Unsupported MSR")`, the same way the original Linux-only
+//! `msr_read` did, so callers can keep treating EIO-equivalents uniformly.
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+mod freebsd;
+#[cfg(target_os = "openbsd")]
+mod openbsd;
+
+#[cfg(target_os = "linux")]
+pub use linux::LinuxMsr as Msr;
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+pub use freebsd::FreeBsdMsr as Msr;
+#[cfg(target_os = "openbsd")]
+pub use openbsd::OpenBsdMsr as Msr;
+
+/// Uniform RDMSR/WRMSR surface over whatever device/ioctl a platform uses.
+pub trait MsrBackend: Sized {
+    /// Open the MSR interface for a given (logical) core.
+    fn open(core_id: usize) -> Result<Self, &'static str>;
+    /// Read an MSR. Returns `Err("Unsupported MSR")` if the read faults.
+    fn read(&self, msr: u32) -> Result<u64, &'static str>;
+    /// Write an MSR. Returns `Err("Unsupported MSR")` if the write faults.
+    fn write(&self, msr: u32, val: u64) -> Result<(), &'static str>;
+    /// Close the MSR interface.
+    fn close(self);
+}
@@ -0,0 +1,28 @@
+//! OpenBSD backend.
+//!
+//! Unlike Linux and the other BSDs, OpenBSD exposes no generic device or
+//! ioctl for arbitrary RDMSR/WRMSR from userspace (flashrom's OpenBSD MSR
+//! support is similarly limited/disabled for the same reason). We still
+//! implement `MsrBackend` so the rest of the crate stays platform-agnostic,
+//! but every access reports itself as unsupported rather than pretending to
+//! work.
+
+use super::MsrBackend;
+
+pub struct OpenBsdMsr;
+
+impl MsrBackend for OpenBsdMsr {
+    fn open(_core_id: usize) -> Result<Self, &'static str> {
+        Err("MSR access is not supported on OpenBSD")
+    }
+
+    fn read(&self, _msr: u32) -> Result<u64, &'static str> {
+        Err("MSR access is not supported on OpenBSD")
+    }
+
+    fn write(&self, _msr: u32, _val: u64) -> Result<(), &'static str> {
+        Err("MSR access is not supported on OpenBSD")
+    }
+
+    fn close(self) {}
+}
@@ -0,0 +1,94 @@
+//! CPUID-based identification, used to pick MSR scan ranges instead of
+//! hardcoding them for one machine (the original 3950X this crate was
+//! written on).
+
+use std::ops::Range;
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::__cpuid;
+
+/// CPU vendor, as decoded from the CPUID leaf 0 vendor string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vendor {
+    Amd,
+    Intel,
+    Unknown(String),
+}
+
+/// Family/model/stepping, as decoded from the CPUID leaf 1 version info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuVersion {
+    pub family: u32,
+    pub model: u32,
+    pub stepping: u32,
+}
+
+/// Read the 12-byte vendor ID string from CPUID leaf 0 (EBX:EDX:ECX).
+pub fn vendor() -> Vendor {
+    let res = unsafe { __cpuid(0) };
+    let mut bytes = [0u8; 12];
+    bytes[0..4].copy_from_slice(&res.ebx.to_le_bytes());
+    bytes[4..8].copy_from_slice(&res.edx.to_le_bytes());
+    bytes[8..12].copy_from_slice(&res.ecx.to_le_bytes());
+    match std::str::from_utf8(&bytes) {
+        Ok("AuthenticAMD") => Vendor::Amd,
+        Ok("GenuineIntel") => Vendor::Intel,
+        Ok(s) => Vendor::Unknown(s.to_string()),
+        Err(_) => Vendor::Unknown(String::from("<invalid vendor string>")),
+    }
+}
+
+/// Decode family/model/stepping from CPUID leaf 1, handling the
+/// extended-family/extended-model encoding used by both AMD and Intel.
+pub fn version() -> CpuVersion {
+    let eax = unsafe { __cpuid(1) }.eax;
+    let base_family = (eax >> 8) & 0xf;
+    let base_model = (eax >> 4) & 0xf;
+    let ext_family = (eax >> 20) & 0xff;
+    let ext_model = (eax >> 16) & 0xf;
+    let stepping = eax & 0xf;
+
+    let family = if base_family == 0xf {
+        base_family + ext_family
+    } else {
+        base_family
+    };
+    let model = if base_family == 0x6 || base_family == 0xf {
+        (ext_model << 4) | base_model
+    } else {
+        base_model
+    };
+
+    CpuVersion { family, model, stepping }
+}
+
+/// Pick the MSR scan ranges for a given vendor/family, refusing to run on
+/// anything we don't have a table for rather than silently scanning
+/// whatever happened to work on one machine (the getrandom crate takes the
+/// same stance: it gates RDRAND/RDSEED use on a known-good family before
+/// trusting the instruction at all).
+pub fn scan_ranges_for(vendor: &Vendor, family: u32) -> Result<Vec<Range<u32>>, &'static str> {
+    match vendor {
+        // Zen (family 0x17) and Zen-derived (0x18, 0x19) parts all share the
+        // same architectural + SMM/APM range layout that the original
+        // 3950X-only constants covered.
+        Vendor::Amd if matches!(family, 0x17 | 0x18 | 0x19) => Ok(vec![
+            0x0000_0000..0x0000_1000,
+            0xc000_0000..0xc002_0000,
+        ]),
+        // Intel has reused family 0x6 for everything from the Pentium Pro
+        // through current Core/Xeon parts, and family 0xf for the older
+        // Netburst (Pentium 4) line; both share this MSR layout. Anything
+        // else is a family we have no table for.
+        // Base architectural range already covers everything relevant here:
+        // IA32_MISC_ENABLE (0x1A0), IA32_PERF_STATUS/IA32_PERF_CTL
+        // (0x198/0x199), MSR_TURBO_RATIO_LIMIT/MSR_TURBO_RATIO_LIMIT1
+        // (0x1AD/0x1AE), and IA32_ENERGY_PERF_BIAS (0x1B0).
+        Vendor::Intel if matches!(family, 0x6 | 0xf) => Ok(vec![
+            0x0000_0000..0x0000_1000,
+        ]),
+        Vendor::Amd => Err("Unrecognized AMD family; no known MSR scan ranges"),
+        Vendor::Intel => Err("Unrecognized Intel family; no known MSR scan ranges"),
+        Vendor::Unknown(_) => Err("Unrecognized CPU vendor; no known MSR scan ranges"),
+    }
+}
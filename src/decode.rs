@@ -0,0 +1,159 @@
+//! Symbolic decoding for well-known architectural MSRs, so the scanner's
+//! output can read as "IA32_MISC_ENABLE, turbo disabled" instead of a wall
+//! of hex. Mirrors the named-register/bit-constant tables kernel MSR
+//! modules (and tools like `rdmsr`/`wrmsr` from msr-tools) keep for exactly
+//! this purpose.
+
+const IA32_APIC_BASE: u32 = 0x0000_001b;
+const IA32_MISC_ENABLE: u32 = 0x0000_01a0;
+const IA32_ENERGY_PERF_BIAS: u32 = 0x0000_01b0;
+const IA32_MTRRCAP: u32 = 0x0000_00fe;
+const IA32_MTRR_DEF_TYPE: u32 = 0x0000_02ff;
+const IA32_PAT: u32 = 0x0000_0277;
+const MTRR_PHYSBASE0: u32 = 0x0000_0200;
+const MTRR_PHYSMASK0: u32 = 0x0000_0201;
+const MTRR_PHYS_PAIR_COUNT: u32 = 8;
+
+/// A decoded MSR: its architectural name, plus whichever sub-fields we know
+/// how to pull apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    pub name: &'static str,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+/// Look up `msr` in the architectural MSR table and decode `val` against
+/// it. Returns `None` for anything we don't have a decoder for, so callers
+/// can fall back to a raw hex dump.
+pub fn describe(msr: u32, val: u64) -> Option<Decoded> {
+    match msr {
+        IA32_APIC_BASE => Some(decode_apic_base(val)),
+        IA32_MISC_ENABLE => Some(decode_misc_enable(val)),
+        IA32_ENERGY_PERF_BIAS => Some(decode_energy_perf_bias(val)),
+        IA32_MTRRCAP => Some(decode_mtrrcap(val)),
+        IA32_MTRR_DEF_TYPE => Some(decode_mtrr_def_type(val)),
+        IA32_PAT => Some(decode_pat(val)),
+        m if is_mtrr_physbase(m) => Some(decode_mtrr_physbase(val)),
+        m if is_mtrr_physmask(m) => Some(decode_mtrr_physmask(val)),
+        _ => None,
+    }
+}
+
+fn is_mtrr_physbase(msr: u32) -> bool {
+    msr >= MTRR_PHYSBASE0
+        && msr < MTRR_PHYSBASE0 + 2 * MTRR_PHYS_PAIR_COUNT
+        && (msr - MTRR_PHYSBASE0) % 2 == 0
+}
+
+fn is_mtrr_physmask(msr: u32) -> bool {
+    msr >= MTRR_PHYSMASK0
+        && msr < MTRR_PHYSMASK0 + 2 * MTRR_PHYS_PAIR_COUNT
+        && (msr - MTRR_PHYSMASK0) % 2 == 0
+}
+
+fn decode_apic_base(val: u64) -> Decoded {
+    Decoded {
+        name: "IA32_APIC_BASE",
+        fields: vec![
+            ("BSP", ((val >> 8) & 1).to_string()),
+            ("EXTD (x2APIC)", ((val >> 10) & 1).to_string()),
+            ("EN (APIC global enable)", ((val >> 11) & 1).to_string()),
+            ("APIC base address", format!("{:#x}", val & 0xf_ffff_f000)),
+        ],
+    }
+}
+
+fn decode_misc_enable(val: u64) -> Decoded {
+    Decoded {
+        name: "IA32_MISC_ENABLE",
+        fields: vec![
+            ("Fast-string enable (bit 0)", ((val >> 0) & 1).to_string()),
+            ("Turbo/IDA disable (bit 38)", ((val >> 38) & 1).to_string()),
+        ],
+    }
+}
+
+fn decode_energy_perf_bias(val: u64) -> Decoded {
+    Decoded {
+        name: "IA32_ENERGY_PERF_BIAS",
+        fields: vec![("Energy Policy Preference (bits 3:0)", (val & 0xf).to_string())],
+    }
+}
+
+fn decode_mtrrcap(val: u64) -> Decoded {
+    Decoded {
+        name: "IA32_MTRRCAP",
+        fields: vec![
+            ("VCNT (variable range count)", (val & 0xff).to_string()),
+            ("FIX (fixed range support)", ((val >> 8) & 1).to_string()),
+            ("WC (write-combining support)", ((val >> 10) & 1).to_string()),
+        ],
+    }
+}
+
+fn decode_mtrr_def_type(val: u64) -> Decoded {
+    Decoded {
+        name: "IA32_MTRR_DEF_TYPE",
+        fields: vec![
+            ("Default memory type (bits 7:0)", memory_type(val & 0xff)),
+            ("FE (fixed MTRR enable)", ((val >> 10) & 1).to_string()),
+            ("E (MTRR enable)", ((val >> 11) & 1).to_string()),
+        ],
+    }
+}
+
+fn decode_pat(val: u64) -> Decoded {
+    let mut fields = Vec::with_capacity(8);
+    for i in 0..8 {
+        let entry = (val >> (i * 8)) & 0xff;
+        // Encoding 7 is reserved for MTRRs but means UC- in a PAT entry.
+        let name = if entry == 7 { "UC- (Uncached)".to_string() } else { memory_type(entry) };
+        fields.push((pat_entry_name(i), name));
+    }
+    Decoded { name: "IA32_PAT", fields }
+}
+
+fn pat_entry_name(i: usize) -> &'static str {
+    match i {
+        0 => "PA0",
+        1 => "PA1",
+        2 => "PA2",
+        3 => "PA3",
+        4 => "PA4",
+        5 => "PA5",
+        6 => "PA6",
+        _ => "PA7",
+    }
+}
+
+fn decode_mtrr_physbase(val: u64) -> Decoded {
+    Decoded {
+        name: "MTRR_PHYSBASEn",
+        fields: vec![
+            ("Type", memory_type(val & 0xff)),
+            ("Base address", format!("{:#x}", val & !0xfffu64)),
+        ],
+    }
+}
+
+fn decode_mtrr_physmask(val: u64) -> Decoded {
+    Decoded {
+        name: "MTRR_PHYSMASKn",
+        fields: vec![
+            ("V (valid)", ((val >> 11) & 1).to_string()),
+            ("Mask", format!("{:#x}", val & !0xfffu64)),
+        ],
+    }
+}
+
+/// Decode an MTRR/PAT memory-type encoding into its architectural name.
+fn memory_type(encoding: u64) -> String {
+    match encoding {
+        0 => "UC (Uncacheable)".to_string(),
+        1 => "WC (Write Combining)".to_string(),
+        4 => "WT (Write Through)".to_string(),
+        5 => "WP (Write Protected)".to_string(),
+        6 => "WB (Write Back)".to_string(),
+        other => format!("Reserved ({:#x})", other),
+    }
+}
@@ -1,59 +1,172 @@
-//! Goofy way of enumerating "acceptable" MSRs via /dev/cpu/n/msr, where the
-//! word "acceptable" here means "cases where RDMSR doesn't fault."
+//! Goofy way of enumerating "acceptable" MSRs via the platform MSR backend,
+//! where the word "acceptable" here means "cases where RDMSR/WRMSR doesn't
+//! fault."
 
+mod backend;
+mod cpuid;
+mod decode;
+mod policy;
+mod topology;
+
+use backend::{Msr, MsrBackend};
 use std::collections::BTreeMap;
 
-// I ran through the entire 32-bit space of ECX values on my 3950X, and 
-// there was nothing outside the expected ranges of architectural MSRs.
-// These ranges should gather all of the acceptable values.
-
-const REGION_LO_3950X: std::ops::Range<u32> = 0x0000_0000..0x0000_1000;
-const REGION_HI_3950X: std::ops::Range<u32> = 0xc000_0000..0xc002_0000;
-
-/// Open the MSR device.
-pub fn msr_open(core_id: usize) -> Result<i32, &'static str> {
-    let path = format!("/dev/cpu/{}/msr", core_id);
-    match nix::fcntl::open(path.as_str(), nix::fcntl::OFlag::O_RDONLY, 
-                           nix::sys::stat::Mode::S_IRUSR) {
-        Ok(fd) => Ok(fd),
-        Err(e) => match e {
-            nix::Error::Sys(eno) => match eno {
-                nix::errno::Errno::EACCES => Err("Permission denied"),
-                _ => panic!("{}", eno),
-            },
-            _ => panic!("{}", e),
-        },
+/// Access class for a single MSR, as determined by probing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessClass {
+    /// RDMSR succeeds, but WRMSR of the original value back faults.
+    ReadOnly,
+    /// RDMSR succeeds, and writing the original value back succeeds too.
+    ReadWrite,
+}
+
+/// Probe whether `msr` is writable without leaving it mutated.
+///
+/// Stashes the original value, writes it straight back, and classifies the
+/// MSR as read-write if that write succeeds. Writing back the value we just
+/// read (instead of some other pattern) means we never actually change
+/// machine state, even transiently, just by probing.
+fn probe_access_class(msr_dev: &Msr, msr: u32, orig: u64) -> AccessClass {
+    match msr_dev.write(msr, orig) {
+        Ok(_) => AccessClass::ReadWrite,
+        Err(_) => AccessClass::ReadOnly,
+    }
+}
+
+/// Behavioral fingerprint of a read-write MSR, from pattern-writing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteMask {
+    /// Bits that actually flipped between the all-ones and all-zeros probes.
+    pub writable: u64,
+    /// Bits that read back 1 no matter what we wrote ("stuck-high").
+    pub stuck_high: u64,
+    /// Bits that read back 0 no matter what we wrote ("stuck-low").
+    pub stuck_low: u64,
+}
+
+/// Determine exactly which bits of a read-write MSR are writable.
+///
+/// Writes `0xFFFF_FFFF_FFFF_FFFF` and `0x0` in turn and reads back after
+/// each, then restores `orig`. Some MSRs fault the *entire* write when a
+/// reserved bit is set to 1, rather than silently masking it off, so if the
+/// all-ones write faults we fall back to flipping one bit at a time against
+/// `orig` to see which individual bits are accepted.
+fn discover_write_mask(msr_dev: &Msr, msr: u32, orig: u64) -> Result<WriteMask, &'static str> {
+    let probe = || -> Result<WriteMask, &'static str> {
+        match msr_dev.write(msr, u64::MAX) {
+            Ok(_) => {
+                let r1 = msr_dev.read(msr)?;
+                msr_dev.write(msr, 0)?;
+                let r0 = msr_dev.read(msr)?;
+                Ok(WriteMask {
+                    writable: r1 ^ r0,
+                    stuck_high: r1 & r0,
+                    stuck_low: !r1 & !r0,
+                })
+            }
+            Err(_) => bit_walk_write_mask(msr_dev, msr, orig),
+        }
+    };
+    let result = probe();
+    // Always attempt to restore `orig`, even if probing itself failed
+    // partway through, so a failed restore is never masked by an earlier
+    // `?` bailing out before we even try.
+    match (result, restore_best_effort(msr_dev, msr, orig)) {
+        (Ok(mask), Ok(())) => Ok(mask),
+        (Ok(_), Err(e)) => Err(e),
+        (Err(e), _) => Err(e),
     }
 }
 
-/// Close the MSR device.
-pub fn msr_close(fd: i32) {
-    use nix::unistd::close;
-    match close(fd) {
-        Ok(_) => {},
-        Err(e) => panic!("{}", e),
+/// Write `orig` back to `msr`, retrying a few times before giving up.
+///
+/// A single failed restore would leave the MSR silently mutated from
+/// whatever pattern we just probed it with, so this keeps retrying rather
+/// than abandoning the MSR in that state after one failed attempt.
+fn restore_best_effort(msr_dev: &Msr, msr: u32, orig: u64) -> Result<(), &'static str> {
+    const ATTEMPTS: u32 = 3;
+    let mut last = Ok(());
+    for _ in 0..ATTEMPTS {
+        last = msr_dev.write(msr, orig);
+        if last.is_ok() {
+            break;
+        }
     }
+    last
 }
 
-/// Test an MSR.
-pub fn msr_read(fd: i32, msr: u32) -> Result<u64, &'static str> {
-    let mut buf = [0u8; 8];
-    match nix::sys::uio::pread(fd, &mut buf, msr as i64) {
-        Ok(_) => Ok(u64::from_le_bytes(buf)),
-        Err(e) => match e {
-            nix::Error::Sys(eno) => match eno {
-                nix::errno::Errno::EIO => Err("Unsupported MSR"),
-                _ => panic!("{}", eno),
-            },
-            _ => panic!("{}", e),
-        },
+/// Fallback for `discover_write_mask` on MSRs where writing all-ones faults
+/// outright: flip each bit of `orig` in turn and see whether it's accepted.
+fn bit_walk_write_mask(msr_dev: &Msr, msr: u32, orig: u64) -> Result<WriteMask, &'static str> {
+    let mut writable = 0u64;
+    let mut stuck_high = 0u64;
+    let mut stuck_low = 0u64;
+    let mut restore_failed = false;
+    for bit in 0..64 {
+        let flipped = orig ^ (1u64 << bit);
+        match msr_dev.write(msr, flipped) {
+            Ok(_) => {
+                // Read the flipped value, then restore unconditionally
+                // (even if the read itself failed) before letting `?`
+                // propagate, so we never leave this bit flipped just
+                // because classifying it went wrong.
+                let read_result = msr_dev.read(msr);
+                if restore_best_effort(msr_dev, msr, orig).is_err() {
+                    restore_failed = true;
+                }
+                let r = read_result?;
+                if (r >> bit) & 1 != (orig >> bit) & 1 {
+                    writable |= 1 << bit;
+                } else if (orig >> bit) & 1 == 1 {
+                    stuck_high |= 1 << bit;
+                } else {
+                    stuck_low |= 1 << bit;
+                }
+            }
+            // The whole write faulted just from flipping this one bit: it's
+            // a reserved bit that must stay at its original value.
+            Err(_) => {
+                if (orig >> bit) & 1 == 1 {
+                    stuck_high |= 1 << bit;
+                } else {
+                    stuck_low |= 1 << bit;
+                }
+            }
+        }
     }
+    if restore_failed {
+        return Err("Failed to restore MSR to its original value during bit-walk probing");
+    }
+    Ok(WriteMask { writable, stuck_high, stuck_low })
+}
+
+/// Where to save this scan's policy buffer and/or which prior one to diff
+/// it against, as parsed from `--save <path>` / `--diff <path>` flags.
+#[derive(Debug, Default)]
+struct Args {
+    save_path: Option<String>,
+    diff_path: Option<String>,
+}
+
+fn parse_args() -> Result<Args, &'static str> {
+    let mut args = Args::default();
+    let mut raw = std::env::args().skip(1);
+    while let Some(flag) = raw.next() {
+        match flag.as_str() {
+            "--save" => args.save_path = Some(raw.next().ok_or("--save requires a path")?),
+            "--diff" => args.diff_path = Some(raw.next().ok_or("--diff requires a path")?),
+            _ => return Err("Usage: msrfuzz [--save <path>] [--diff <baseline path>]"),
+        }
+    }
+    Ok(args)
 }
 
 fn main() -> Result<(), &'static str> {
+    let args = parse_args()?;
 
     const TGT_CORE: usize = 0;
-    let mut output = BTreeMap::new();
+    let mut output: BTreeMap<u32, (u64, AccessClass)> = BTreeMap::new();
+    let mut masks: BTreeMap<u32, WriteMask> = BTreeMap::new();
 
     // Pin to the same core we're reading from.
     // You get a ~10x slowdown when you're not doing this (lol).
@@ -63,28 +176,126 @@ fn main() -> Result<(), &'static str> {
     cpuset.set(TGT_CORE).unwrap();
     nix::sched::sched_setaffinity(this_pid, &cpuset).unwrap();
 
-    let fd = match msr_open(TGT_CORE) {
-        Ok(fd) => fd,
-        Err(e) => return Err(e),
-    };
+    let msr_dev = Msr::open(TGT_CORE)?;
+
+    let vendor = cpuid::vendor();
+    let version = cpuid::version();
+    eprintln!("CPU: {:?} family={:#x} model={:#x}", vendor, version.family, version.model);
+    let ranges = cpuid::scan_ranges_for(&vendor, version.family)?;
 
-    for msr in REGION_LO_3950X {
-        if let Ok(val) = msr_read(fd, msr) {
-            eprintln!("Found MSR {:08x}", msr);
-            output.insert(msr, val);
+    for msr in ranges.into_iter().flatten() {
+        if let Ok(val) = msr_dev.read(msr) {
+            let class = probe_access_class(&msr_dev, msr, val);
+            eprintln!("Found MSR {:08x} ({:?})", msr, class);
+            if class == AccessClass::ReadWrite {
+                if let Ok(mask) = discover_write_mask(&msr_dev, msr, val) {
+                    masks.insert(msr, mask);
+                }
+            }
+            output.insert(msr, (val, class));
         }
     }
-    for msr in REGION_HI_3950X {
-        if let Ok(val) = msr_read(fd, msr) {
-            eprintln!("Found MSR {:08x}", msr);
-            output.insert(msr, val);
+
+    for (msr, (val, class)) in &output {
+        let tag = match class {
+            AccessClass::ReadOnly => "ro",
+            AccessClass::ReadWrite => "rw",
+        };
+        println!("{:08x}: {:016x} [{}]", msr, val, tag);
+        if let Some(mask) = masks.get(msr) {
+            println!(
+                "         writable={:016x} stuck_high={:016x} stuck_low={:016x}",
+                mask.writable, mask.stuck_high, mask.stuck_low
+            );
+        }
+        if let Some(decoded) = decode::describe(*msr, *val) {
+            println!("         {}", decoded.name);
+            for (field, value) in &decoded.fields {
+                println!("           {}: {}", field, value);
+            }
         }
     }
 
-    for (msr, val) in &output {
-        println!("{:08x}: {:016x}", msr, val);
+    msr_dev.close();
+
+    // Re-read every discovered MSR across all online cores, to see which of
+    // them are actually per-core state rather than package-shared.
+    let discovered: Vec<u32> = output.keys().copied().collect();
+    let topo = topology::scan_topology(&discovered)?;
+    for (msr, (scope, values)) in &topo {
+        eprintln!("{:08x}: {:?} {:x?}", msr, scope, values);
+    }
+
+    // Package the scan into a portable policy buffer, so it can be dumped
+    // to disk and diffed against a scan from another machine or a
+    // pre/post-microcode snapshot of this one.
+    let core_count = topo
+        .values()
+        .next()
+        .map(|(_, values)| values.len() as u32)
+        .unwrap_or(1);
+    let header = policy::PolicyHeader {
+        vendor: format!("{:?}", vendor),
+        family: version.family,
+        model: version.model,
+        core_count,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    let mut entries = BTreeMap::new();
+    for (&msr, &(val, class)) in &output {
+        let mut flags = policy::FLAG_READABLE;
+        if class == AccessClass::ReadWrite {
+            flags |= policy::FLAG_WRITABLE;
+        }
+        if let Some(mask) = masks.get(&msr) {
+            if mask.stuck_high != 0 {
+                flags |= policy::FLAG_STUCK_HIGH;
+            }
+            if mask.stuck_low != 0 {
+                flags |= policy::FLAG_STUCK_LOW;
+            }
+        }
+        entries.insert(msr, policy::Entry { index: msr, value: val, flags });
+    }
+    let buf = policy::to_buffer(&header, &entries);
+    eprintln!("Serialized {} entries into a {}-byte policy buffer", entries.len(), buf.len());
+
+    if let Some(path) = &args.save_path {
+        std::fs::write(path, &buf).map_err(|_| "Failed to write policy buffer to disk")?;
+        eprintln!("Saved policy buffer to {}", path);
+    }
+
+    if let Some(path) = &args.diff_path {
+        let baseline_buf = std::fs::read(path).map_err(|_| "Failed to read baseline policy buffer")?;
+        let (baseline_header, baseline_entries) = policy::from_buffer(&baseline_buf)?;
+        eprintln!(
+            "Diffing against baseline: {} family={:#x} model={:#x} ({} cores)",
+            baseline_header.vendor, baseline_header.family, baseline_header.model, baseline_header.core_count,
+        );
+        let changes = policy::diff(&baseline_entries, &entries);
+        if changes.is_empty() {
+            println!("No differences from baseline {}", path);
+        }
+        for (msr, change) in &changes {
+            match change {
+                policy::EntryDiff::OnlyInA(e) => {
+                    println!("{:08x}: only in baseline (value={:016x} flags={:#04x})", msr, e.value, e.flags);
+                }
+                policy::EntryDiff::OnlyInB(e) => {
+                    println!("{:08x}: only in this scan (value={:016x} flags={:#04x})", msr, e.value, e.flags);
+                }
+                policy::EntryDiff::Changed { a, b } => {
+                    println!(
+                        "{:08x}: {:016x} [{:#04x}] -> {:016x} [{:#04x}]",
+                        msr, a.value, a.flags, b.value, b.flags,
+                    );
+                }
+            }
+        }
     }
 
-    msr_close(fd);
     Ok(())
 }
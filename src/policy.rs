@@ -0,0 +1,157 @@
+//! Portable "MSR policy" buffer: a compact, versioned binary snapshot of a
+//! scan, so a baseline captured on one machine can be compared against
+//! another (or against the same machine pre/post a microcode update).
+//!
+//! The layout follows the same shape as Xen's `libx86`
+//! `x86_msr_copy_from_buffer`/`x86_msr_get_entry`: a small header describing
+//! where the scan came from, followed by a sorted array of fixed-size
+//! entries. Keeping values as full `u64`s (rather than a variable-width
+//! encoding) means nothing is lost to truncation.
+
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+
+const MAGIC: u32 = 0x4d53_5250; // "PRSM" in little-endian bytes
+const VERSION: u8 = 1;
+
+/// A single MSR's recorded state, as it appears in a policy buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub index: u32,
+    pub value: u64,
+    pub flags: u8,
+}
+
+pub const FLAG_READABLE: u8 = 1 << 0;
+pub const FLAG_WRITABLE: u8 = 1 << 1;
+pub const FLAG_STUCK_HIGH: u8 = 1 << 2;
+pub const FLAG_STUCK_LOW: u8 = 1 << 3;
+
+/// Where a scan was taken, so two buffers can be told apart before diffing
+/// values that may not even be comparable (different vendor/family).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyHeader {
+    pub vendor: String,
+    pub family: u32,
+    pub model: u32,
+    pub core_count: u32,
+    pub timestamp: u64,
+}
+
+const ENTRY_SIZE: usize = 4 + 8 + 1;
+
+/// Serialize a completed scan into a policy buffer.
+///
+/// `entries` must already be keyed by MSR index; `BTreeMap` iteration order
+/// guarantees the on-disk array comes out sorted, which `from_buffer`
+/// requires on the way back in.
+pub fn to_buffer(header: &PolicyHeader, entries: &BTreeMap<u32, Entry>) -> Vec<u8> {
+    let vendor_bytes = header.vendor.as_bytes();
+    let mut buf = Vec::with_capacity(32 + vendor_bytes.len() + entries.len() * ENTRY_SIZE);
+
+    buf.extend_from_slice(&MAGIC.to_le_bytes());
+    buf.push(VERSION);
+    buf.extend_from_slice(&header.family.to_le_bytes());
+    buf.extend_from_slice(&header.model.to_le_bytes());
+    buf.extend_from_slice(&header.core_count.to_le_bytes());
+    buf.extend_from_slice(&header.timestamp.to_le_bytes());
+    buf.extend_from_slice(&(vendor_bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(vendor_bytes);
+    buf.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    for entry in entries.values() {
+        buf.extend_from_slice(&entry.index.to_le_bytes());
+        buf.extend_from_slice(&entry.value.to_le_bytes());
+        buf.push(entry.flags);
+    }
+    buf
+}
+
+/// Parse a policy buffer written by `to_buffer`.
+///
+/// Validates the magic/version, that the declared entry count matches the
+/// buffer's actual length (no truncation), and that entry indices are
+/// strictly increasing, since a buffer with duplicate or out-of-order
+/// indices couldn't have been produced by `to_buffer`.
+pub fn from_buffer(buf: &[u8]) -> Result<(PolicyHeader, BTreeMap<u32, Entry>), &'static str> {
+    let mut cursor = 0usize;
+    let mut take = |n: usize| -> Result<&[u8], &'static str> {
+        let slice = buf.get(cursor..cursor + n).ok_or("Truncated policy buffer")?;
+        cursor += n;
+        Ok(slice)
+    };
+
+    let magic = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    if magic != MAGIC {
+        return Err("Bad policy buffer magic");
+    }
+    let version = take(1)?[0];
+    if version != VERSION {
+        return Err("Unsupported policy buffer version");
+    }
+    let family = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let model = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let core_count = u32::from_le_bytes(take(4)?.try_into().unwrap());
+    let timestamp = u64::from_le_bytes(take(8)?.try_into().unwrap());
+    let vendor_len = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+    let vendor = String::from_utf8(take(vendor_len)?.to_vec()).map_err(|_| "Invalid vendor string")?;
+    let entry_count = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+
+    let mut entries = BTreeMap::new();
+    let mut last_index: Option<u32> = None;
+    for _ in 0..entry_count {
+        let index = u32::from_le_bytes(take(4)?.try_into().unwrap());
+        let value = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let flags = take(1)?[0];
+        if let Some(last) = last_index {
+            if index <= last {
+                return Err("Policy buffer entries are not strictly increasing");
+            }
+        }
+        last_index = Some(index);
+        entries.insert(index, Entry { index, value, flags });
+    }
+
+    if cursor != buf.len() {
+        return Err("Trailing bytes after policy buffer entries");
+    }
+
+    Ok((
+        PolicyHeader { vendor, family, model, core_count, timestamp },
+        entries,
+    ))
+}
+
+/// A single MSR's difference between two policy buffers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryDiff {
+    /// Present in `a` only.
+    OnlyInA(Entry),
+    /// Present in `b` only.
+    OnlyInB(Entry),
+    /// Present in both, but value and/or flags differ.
+    Changed { a: Entry, b: Entry },
+}
+
+/// Compare two scans entry-by-entry, reporting additions, removals, and
+/// value/flag deltas.
+pub fn diff(a: &BTreeMap<u32, Entry>, b: &BTreeMap<u32, Entry>) -> BTreeMap<u32, EntryDiff> {
+    let mut out = BTreeMap::new();
+    for (&index, &entry_a) in a {
+        match b.get(&index) {
+            None => {
+                out.insert(index, EntryDiff::OnlyInA(entry_a));
+            }
+            Some(&entry_b) if entry_a != entry_b => {
+                out.insert(index, EntryDiff::Changed { a: entry_a, b: entry_b });
+            }
+            _ => {}
+        }
+    }
+    for (&index, &entry_b) in b {
+        if !a.contains_key(&index) {
+            out.insert(index, EntryDiff::OnlyInB(entry_b));
+        }
+    }
+    out
+}
@@ -0,0 +1,143 @@
+//! Multi-core scanning: reads each discovered MSR on every online core and
+//! classifies whether its value is shared package-wide, varies per physical
+//! core, or varies per SMT sibling.
+//!
+//! This turns the single-snapshot scan (always pinned to core 0) into a
+//! topology-aware map, useful for spotting core-local state like
+//! APIC-base or SMM/debug MSRs that a package-wide baseline would hide.
+
+use crate::backend::{Msr, MsrBackend};
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::thread;
+
+/// How a single MSR's value relates to core/thread topology.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreScope {
+    /// Same value read back on every online core.
+    Shared,
+    /// Value differs between physical cores, but agrees between SMT
+    /// siblings of the same physical core.
+    PerCore,
+    /// Value differs even between SMT siblings of the same physical core.
+    PerThread,
+}
+
+/// Every online logical core, paired with the physical core it belongs to.
+fn online_cores() -> Result<Vec<(usize, usize)>, &'static str> {
+    // `nix::unistd::SysconfVar` has no processor-count variant in any nix
+    // release, so this goes straight to the libc syscall nix re-exports.
+    let n = unsafe { nix::libc::sysconf(nix::libc::_SC_NPROCESSORS_ONLN) };
+    if n < 0 {
+        return Err("Failed to query online CPU count");
+    }
+    let n = n as usize;
+
+    let mut cores = Vec::with_capacity(n);
+    for logical in 0..n {
+        cores.push((logical, physical_core_id(logical).unwrap_or(logical)));
+    }
+    Ok(cores)
+}
+
+/// Read `/sys/devices/system/cpu/cpuN/topology/core_id`, which groups SMT
+/// siblings under the same physical core id on Linux.
+#[cfg(target_os = "linux")]
+fn physical_core_id(logical: usize) -> Option<usize> {
+    let path = format!("/sys/devices/system/cpu/cpu{}/topology/core_id", logical);
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn physical_core_id(_logical: usize) -> Option<usize> {
+    None
+}
+
+/// Read `msrs` on every online core, in a thread pinned to that core (the
+/// same pinning trick `main` already uses for its single-core scan, since
+/// unpinned reads are ~10x slower).
+fn read_all_cores(msrs: &[u32]) -> Result<BTreeMap<usize, BTreeMap<u32, u64>>, &'static str> {
+    let cores = online_cores()?;
+    let (tx, rx) = mpsc::channel();
+
+    for (logical, _physical) in &cores {
+        let tx = tx.clone();
+        let msrs = msrs.to_vec();
+        let logical = *logical;
+        thread::spawn(move || {
+            let this_pid = nix::unistd::Pid::from_raw(0);
+            let mut cpuset = nix::sched::CpuSet::new();
+            cpuset.set(logical).unwrap();
+            nix::sched::sched_setaffinity(this_pid, &cpuset).unwrap();
+
+            let mut values = BTreeMap::new();
+            if let Ok(dev) = Msr::open(logical) {
+                for &msr in &msrs {
+                    if let Ok(val) = dev.read(msr) {
+                        values.insert(msr, val);
+                    }
+                }
+                dev.close();
+            }
+            let _ = tx.send((logical, values));
+        });
+    }
+    drop(tx);
+
+    let mut per_core = BTreeMap::new();
+    for (logical, values) in rx {
+        per_core.insert(logical, values);
+    }
+    Ok(per_core)
+}
+
+/// Scan `msrs` across every online core and classify each by [`CoreScope`].
+///
+/// Returns, per MSR, its scope plus the raw per-logical-core values so
+/// callers can inspect the disagreement themselves.
+pub fn scan_topology(
+    msrs: &[u32],
+) -> Result<BTreeMap<u32, (CoreScope, BTreeMap<usize, u64>)>, &'static str> {
+    let cores = online_cores()?;
+    let per_core = read_all_cores(msrs)?;
+
+    let mut out = BTreeMap::new();
+    for &msr in msrs {
+        let values: BTreeMap<usize, u64> = per_core
+            .iter()
+            .filter_map(|(logical, vals)| vals.get(&msr).map(|v| (*logical, *v)))
+            .collect();
+        if values.is_empty() {
+            continue;
+        }
+
+        let all_equal = values.values().all(|v| *v == *values.values().next().unwrap());
+        let scope = if all_equal {
+            CoreScope::Shared
+        } else if siblings_agree(&cores, &values) {
+            CoreScope::PerCore
+        } else {
+            CoreScope::PerThread
+        };
+        out.insert(msr, (scope, values));
+    }
+    Ok(out)
+}
+
+/// Whether every pair of logical cores sharing a physical core id agree on
+/// their value, i.e. the variation we see is core-to-core, not
+/// thread-to-thread within a core.
+fn siblings_agree(cores: &[(usize, usize)], values: &BTreeMap<usize, u64>) -> bool {
+    let mut by_physical: BTreeMap<usize, u64> = BTreeMap::new();
+    for (logical, physical) in cores {
+        if let Some(val) = values.get(logical) {
+            match by_physical.get(physical) {
+                Some(seen) if seen != val => return false,
+                _ => {
+                    by_physical.insert(*physical, *val);
+                }
+            }
+        }
+    }
+    true
+}